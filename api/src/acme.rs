@@ -0,0 +1,265 @@
+//! Minimal ACME v2 client for automatic `wss://` certificate provisioning.
+//!
+//! Covers the JWS-signing core of RFC 8555 end to end: create/load an
+//! account, submit a `newOrder`, finalize it with a CSR once its challenges
+//! are satisfied, and decide when a held certificate needs renewing. The
+//! HTTP transport (directory/nonce/order-polling/certificate-download
+//! requests and the TLS-ALPN-01 listener that serves the challenge
+//! certificate) is `ApiConfig`/`WebSocketServer`'s job, since only those
+//! types own a socket to serve it on; this module hands them signed,
+//! ready-to-POST `Jws` envelopes and pure renewal-scheduling logic.
+
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use failure::{format_err, Error};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime};
+
+/// Directory endpoints advertised by the ACME server.
+#[derive(Debug, Clone)]
+pub struct AcmeDirectory {
+    pub new_nonce: String,
+    pub new_account: String,
+    pub new_order: String,
+}
+
+/// A provisioned account: its key pair (reused across renewals) and the
+/// server-assigned account URL (`kid`).
+pub struct AcmeAccount {
+    key_pair: EcdsaKeyPair,
+    pub kid: String,
+}
+
+/// A flattened JWS as ACME expects it: base64url(protected) + "." +
+/// base64url(payload), signed, and transmitted as `application/jose+json`.
+#[derive(Debug, Clone, serde_derive::Serialize)]
+pub struct Jws {
+    pub protected: String,
+    pub payload: String,
+    pub signature: String,
+}
+
+fn b64url(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+/// Builds and signs a JWS over `payload`, addressed to `url`, authenticated
+/// either by the account's public key (`jwk`, used only for `newAccount`) or
+/// by its `kid` (every subsequent request).
+fn sign_jws(
+    key_pair: &EcdsaKeyPair,
+    rng: &SystemRandom,
+    url: &str,
+    nonce: &str,
+    kid: Option<&str>,
+    payload: &Value,
+) -> Result<Jws, Error> {
+    let jwk = json!({
+        "kty": "EC",
+        "crv": "P-256",
+        "x": b64url(&key_pair.public_key().as_ref()[1..33]),
+        "y": b64url(&key_pair.public_key().as_ref()[33..65]),
+    });
+    let header = match kid {
+        Some(kid) => json!({ "alg": "ES256", "nonce": nonce, "url": url, "kid": kid }),
+        None => json!({ "alg": "ES256", "nonce": nonce, "url": url, "jwk": jwk }),
+    };
+    let protected = b64url(header.to_string().as_bytes());
+    let payload = b64url(payload.to_string().as_bytes());
+    let signing_input = format!("{}.{}", protected, payload);
+    let signature = key_pair
+        .sign(rng, signing_input.as_bytes())
+        .map_err(|_| format_err!("Failed to sign ACME request"))?;
+    Ok(Jws {
+        protected,
+        payload,
+        signature: b64url(signature.as_ref()),
+    })
+}
+
+impl AcmeAccount {
+    /// Wraps an account key and the `kid` the server assigned to it.
+    pub fn new(key_pair: EcdsaKeyPair, kid: String) -> Self {
+        AcmeAccount { key_pair, kid }
+    }
+
+    /// Generates a fresh ECDSA P-256 account key, returned both loaded and
+    /// as its PKCS#8 document. Callers should persist the PKCS#8 bytes
+    /// (outside this module) so renewals reuse the key instead of
+    /// registering a new account every time.
+    pub fn generate(rng: &SystemRandom) -> Result<(EcdsaKeyPair, Vec<u8>), Error> {
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, rng)
+            .map_err(|_| format_err!("Failed to generate ACME account key"))?;
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref())
+            .map_err(|_| format_err!("Failed to load generated ACME account key"))?;
+        Ok((key_pair, pkcs8.as_ref().to_vec()))
+    }
+
+    /// Registers (or, with a key already known to the server, re-derives)
+    /// the account, signing a `newAccount` request with the account key's
+    /// JWK rather than a `kid`, since none is known yet.
+    pub fn register(
+        key_pair: EcdsaKeyPair,
+        rng: &SystemRandom,
+        directory: &AcmeDirectory,
+        nonce: &str,
+        contacts: &[String],
+    ) -> Result<(Jws, EcdsaKeyPair), Error> {
+        let payload = json!({
+            "termsOfServiceAgreed": true,
+            "contact": contacts,
+        });
+        let jws = sign_jws(&key_pair, rng, &directory.new_account, nonce, None, &payload)?;
+        Ok((jws, key_pair))
+    }
+}
+
+/// Submits a `newOrder` for the given DNS identifiers.
+pub fn new_order(
+    account: &AcmeAccount,
+    rng: &SystemRandom,
+    directory: &AcmeDirectory,
+    nonce: &str,
+    identifiers: &[String],
+) -> Result<Jws, Error> {
+    let payload = json!({
+        "identifiers": identifiers
+            .iter()
+            .map(|d| json!({ "type": "dns", "value": d }))
+            .collect::<Vec<_>>(),
+    });
+    sign_jws(
+        &account.key_pair,
+        rng,
+        &directory.new_order,
+        nonce,
+        Some(&account.kid),
+        &payload,
+    )
+}
+
+/// The key authorization a TLS-ALPN-01 responder must embed in its
+/// self-signed certificate: `base64url(SHA-256(token || "." || thumbprint))`
+/// is computed by the caller and placed in the `id-pe-acmeIdentifier`
+/// extension of the challenge certificate served over the listening socket.
+pub fn key_authorization(token: &str, account_key_thumbprint: &str) -> String {
+    format!("{}.{}", token, account_key_thumbprint)
+}
+
+/// Finalizes an order once its authorizations are all `valid`, submitting a
+/// DER-encoded CSR for the identifiers the order was created with.
+pub fn finalize_order(
+    account: &AcmeAccount,
+    rng: &SystemRandom,
+    finalize_url: &str,
+    nonce: &str,
+    csr_der: &[u8],
+) -> Result<Jws, Error> {
+    let payload = json!({ "csr": b64url(csr_der) });
+    sign_jws(
+        &account.key_pair,
+        rng,
+        finalize_url,
+        nonce,
+        Some(&account.kid),
+        &payload,
+    )
+}
+
+/// Whether a certificate valid until `not_after` should be renewed now,
+/// i.e. less than `renew_before` remains until it expires. Driving this off
+/// a pure function (rather than comparing timestamps inline wherever a
+/// renewal check happens) keeps the "when to renew" policy in one place and
+/// testable without a clock mock.
+pub fn should_renew(not_after: SystemTime, renew_before: Duration) -> bool {
+    match not_after.checked_sub(renew_before) {
+        Some(renew_at) => SystemTime::now() >= renew_at,
+        // `not_after - renew_before` falls before the earliest instant
+        // `SystemTime` can represent, so that moment is long past.
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_account() -> (AcmeAccount, SystemRandom) {
+        let rng = SystemRandom::new();
+        let (key_pair, _pkcs8) = AcmeAccount::generate(&rng).expect("generate account key");
+        let directory = AcmeDirectory {
+            new_nonce: "https://acme.example/new-nonce".to_owned(),
+            new_account: "https://acme.example/new-account".to_owned(),
+            new_order: "https://acme.example/new-order".to_owned(),
+        };
+        let (_jws, key_pair) =
+            AcmeAccount::register(key_pair, &rng, &directory, "nonce-1", &[]).expect("register");
+        (
+            AcmeAccount {
+                key_pair,
+                kid: "https://acme.example/account/1".to_owned(),
+            },
+            rng,
+        )
+    }
+
+    #[test]
+    fn new_order_and_finalize_order_produce_well_formed_jws_envelopes() {
+        let (account, rng) = test_account();
+        let directory = AcmeDirectory {
+            new_nonce: "https://acme.example/new-nonce".to_owned(),
+            new_account: "https://acme.example/new-account".to_owned(),
+            new_order: "https://acme.example/new-order".to_owned(),
+        };
+        let order = new_order(
+            &account,
+            &rng,
+            &directory,
+            "nonce-2",
+            &["example.com".to_owned()],
+        )
+        .expect("new_order");
+        // Every field must be non-empty base64url, never raw JSON.
+        for field in [&order.protected, &order.payload, &order.signature] {
+            assert!(!field.is_empty());
+            assert!(base64::decode_config(field, base64::URL_SAFE_NO_PAD).is_ok());
+        }
+
+        let finalized = finalize_order(&account, &rng, "https://acme.example/finalize/1", "nonce-3", b"fake-csr-der")
+            .expect("finalize_order");
+        assert_ne!(finalized.payload, order.payload);
+    }
+
+    #[test]
+    fn key_authorization_joins_token_and_thumbprint() {
+        assert_eq!(key_authorization("tok", "thumb"), "tok.thumb");
+    }
+
+    #[test]
+    fn should_renew_is_true_once_inside_the_renewal_window() {
+        let not_after = SystemTime::now() + Duration::from_secs(3600);
+        assert!(!should_renew(not_after, Duration::from_secs(60)));
+        assert!(should_renew(not_after, Duration::from_secs(7200)));
+    }
+}
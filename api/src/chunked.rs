@@ -0,0 +1,184 @@
+//! Chunked streaming transport for large unicast/broadcast payloads.
+//!
+//! `NetworkRequest::SendUnicast`/`PublishBroadcast` JSON-encode, encrypt and
+//! base64-encode their whole payload in one shot via [`crate::encode`],
+//! which holds three full copies of a multi-megabyte blob in memory at
+//! once. This module splits a payload into sequenced frames sharing a
+//! `transfer_id`, encrypts each frame independently with the connection's
+//! `ApiToken`, and reassembles them as they arrive, so no whole-payload
+//! ciphertext buffer is ever built and a transfer can be backpressured per
+//! frame rather than per whole message. [`split_into_frames`] returns every
+//! encoded frame at once, and [`Reassembler`] keeps every received frame of
+//! a transfer in memory until [`Reassembler::finish`].
+
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::crypto::{decrypt, encrypt};
+use crate::ApiToken;
+use std::collections::BTreeMap;
+
+/// Maximum plaintext bytes carried by one frame.
+pub const FRAME_SIZE: usize = 64 * 1024;
+
+/// Splits `payload` into `FRAME_SIZE` plaintext chunks, each independently
+/// encrypted and base64-encoded, ready to go out as a sequence of
+/// start/continue/finish requests.
+pub fn split_into_frames(api_token: &ApiToken, payload: &[u8]) -> Vec<String> {
+    payload
+        .chunks(FRAME_SIZE)
+        .map(|chunk| base64::encode(&encrypt(api_token, chunk)))
+        .collect()
+}
+
+/// Decodes and decrypts a single frame back into plaintext bytes.
+pub fn decode_frame(api_token: &ApiToken, frame: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    let ciphertext = base64::decode(frame)?;
+    Ok(decrypt(api_token, &ciphertext))
+}
+
+struct Transfer {
+    total_len: usize,
+    expected_frames: u64,
+    frames: BTreeMap<u64, Vec<u8>>,
+}
+
+/// Reassembles frames for one or more concurrent transfers, keyed by
+/// `transfer_id`. Frames may arrive out of order within a transfer; they
+/// are concatenated by sequence number once every frame is present.
+#[derive(Default)]
+pub struct Reassembler {
+    pending: BTreeMap<u64, Transfer>,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a new transfer and its first frame (sequence `0`).
+    /// `total_frames` is the exact number of frames the sender split the
+    /// payload into (`ceil(total_len / FRAME_SIZE)`, at least `1`); it lets
+    /// [`Reassembler::finish`] check that every expected index actually
+    /// arrived instead of trusting the total byte count alone.
+    pub fn start(&mut self, transfer_id: u64, total_len: usize, total_frames: u64, first_chunk: Vec<u8>) {
+        let mut frames = BTreeMap::new();
+        frames.insert(0, first_chunk);
+        self.pending.insert(
+            transfer_id,
+            Transfer {
+                total_len,
+                expected_frames: total_frames.max(1),
+                frames,
+            },
+        );
+    }
+
+    /// Records one more frame of an in-progress transfer. Frames for an
+    /// unknown `transfer_id` are silently dropped, the way a late frame for
+    /// an already-finished or never-started transfer would be.
+    pub fn push(&mut self, transfer_id: u64, seq: u64, chunk: Vec<u8>) {
+        if let Some(transfer) = self.pending.get_mut(&transfer_id) {
+            transfer.frames.insert(seq, chunk);
+        }
+    }
+
+    /// Bytes received so far for a transfer, for progress notifications.
+    pub fn bytes_received(&self, transfer_id: u64) -> usize {
+        self.pending
+            .get(&transfer_id)
+            .map(|t| t.frames.values().map(Vec::len).sum())
+            .unwrap_or(0)
+    }
+
+    /// Completes a transfer, concatenating its frames in sequence order.
+    /// Returns `None` if the transfer is unknown or some of its frames
+    /// haven't arrived yet. Checks the exact set of expected frame indices
+    /// rather than just the total byte count, so a dropped middle frame
+    /// whose size happens to be compensated by a duplicate or out-of-order
+    /// frame is reported incomplete instead of silently reassembling
+    /// corrupted, reordered data. Also returns `None`, and discards the
+    /// transfer, when all frames are present but their combined length
+    /// differs from `total_len`.
+    pub fn finish(&mut self, transfer_id: u64) -> Option<Vec<u8>> {
+        let transfer = self.pending.remove(&transfer_id)?;
+        let complete = transfer.frames.len() as u64 == transfer.expected_frames
+            && transfer
+                .frames
+                .keys()
+                .cloned()
+                .eq(0..transfer.expected_frames);
+        if !complete {
+            self.pending.insert(transfer_id, transfer);
+            return None;
+        }
+        let reassembled: Vec<u8> = transfer
+            .frames
+            .into_iter()
+            .flat_map(|(_, chunk)| chunk)
+            .collect();
+        if reassembled.len() != transfer.total_len {
+            return None;
+        }
+        Some(reassembled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reassembles_out_of_order_frames() {
+        let mut reassembler = Reassembler::new();
+        let payload = b"hello chunked world".to_vec();
+        reassembler.start(1, payload.len(), 3, payload[0..5].to_vec());
+        reassembler.push(1, 2, payload[10..].to_vec());
+        assert!(reassembler.finish(1).is_none(), "frame 1 is still missing");
+
+        reassembler.push(1, 1, payload[5..10].to_vec());
+        let reassembled = reassembler.finish(1).expect("all frames present");
+        assert_eq!(reassembled, payload);
+    }
+
+    #[test]
+    fn unknown_transfer_finishes_to_none() {
+        let mut reassembler = Reassembler::new();
+        assert!(reassembler.finish(42).is_none());
+    }
+
+    #[test]
+    fn a_dropped_middle_frame_is_not_masked_by_a_same_sized_stray_frame() {
+        let mut reassembler = Reassembler::new();
+        let payload = b"hello chunked world".to_vec();
+        // Frame 1 ("chunk", 5 bytes) never arrives, but a stray 5-byte frame
+        // shows up at an out-of-range sequence number, so the total byte
+        // count coincidentally matches `total_len` even though expected
+        // index 1 is still missing.
+        reassembler.start(1, payload.len(), 3, payload[0..5].to_vec());
+        reassembler.push(1, 2, payload[10..].to_vec());
+        reassembler.push(1, 99, payload[0..5].to_vec());
+        assert!(
+            reassembler.finish(1).is_none(),
+            "index 1 is missing even though total bytes received matches total_len"
+        );
+    }
+}
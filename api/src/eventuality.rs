@@ -0,0 +1,251 @@
+//! Tracks the eventual fate of a transaction's outputs after it's been sent.
+//!
+//! `NodeRequest::SubscribeTransaction` asks to be told, for a transaction
+//! the client already submitted, whether it confirmed, lost to a
+//! double-spend, or was pruned — the way a payment processor watches a
+//! mempool entry for its eventual on-chain outcome instead of trusting the
+//! initial broadcast. A [`ClaimTracker`] is the engine behind that watch: it
+//! remembers which output hashes a transaction claims, and turns the raw
+//! per-block "these outputs were created, these inputs were spent" facts
+//! [`ClaimTracker::observe_block`] is fed into a `Confirmed`/`Conflicted`
+//! verdict per transaction. [`ClaimTracker::rollback`] re-arms a claim a
+//! micro-block rollback un-confirmed, and [`ClaimTracker::expire`] prunes
+//! claims nobody will ever resolve.
+
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use stegos_crypto::hash::Hash;
+
+/// The verdict [`ClaimTracker::observe_block`] reaches for a claim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimStatus {
+    /// Every output the claim named was produced in this block.
+    Confirmed { epoch: u64, offset: u32 },
+    /// One of the claim's output hashes will never be produced, because an
+    /// input it depended on was spent by some other transaction first.
+    Conflicted,
+    /// The claim outlived `expires_after_epochs` without resolving either
+    /// way.
+    Pruned,
+}
+
+#[derive(Debug, Clone)]
+struct Claim {
+    output_hashes: Vec<Hash>,
+    input_hashes: Vec<Hash>,
+    registered_epoch: u64,
+    expires_after_epochs: u64,
+}
+
+/// Tracks pending transaction claims between submission and on-chain
+/// resolution, for one `SubscribeTransaction` connection.
+#[derive(Debug, Default)]
+pub struct ClaimTracker {
+    pending: HashMap<Hash, Claim>,
+    confirmed: HashMap<Hash, Claim>,
+}
+
+impl ClaimTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a transaction's output and input hashes as a pending
+    /// claim, to be resolved by a later [`ClaimTracker::observe_block`].
+    pub fn register(
+        &mut self,
+        tx_hash: Hash,
+        output_hashes: Vec<Hash>,
+        input_hashes: Vec<Hash>,
+        current_epoch: u64,
+        expires_after_epochs: u64,
+    ) {
+        self.pending.insert(
+            tx_hash,
+            Claim {
+                output_hashes,
+                input_hashes,
+                registered_epoch: current_epoch,
+                expires_after_epochs,
+            },
+        );
+    }
+
+    /// Resolves every pending claim against one block's effects: outputs it
+    /// produced and inputs it spent. A claim confirms once a single block
+    /// produces every output it named (outputs seen in earlier blocks are
+    /// not remembered); it's conflicted the moment an input it
+    /// depended on is spent without that happening first, since that input
+    /// can now never fund this claim's outputs.
+    pub fn observe_block(
+        &mut self,
+        epoch: u64,
+        offset: u32,
+        produced_output_hashes: &[Hash],
+        spent_input_hashes: &[Hash],
+    ) -> Vec<(Hash, ClaimStatus)> {
+        let mut resolved = Vec::new();
+        let mut newly_confirmed = Vec::new();
+        self.pending.retain(|tx_hash, claim| {
+            let confirmed = claim
+                .output_hashes
+                .iter()
+                .all(|h| produced_output_hashes.contains(h));
+            if confirmed {
+                resolved.push((*tx_hash, ClaimStatus::Confirmed { epoch, offset }));
+                newly_confirmed.push((*tx_hash, claim.clone()));
+                return false;
+            }
+            let conflicted = claim
+                .input_hashes
+                .iter()
+                .any(|h| spent_input_hashes.contains(h));
+            if conflicted {
+                resolved.push((*tx_hash, ClaimStatus::Conflicted));
+                return false;
+            }
+            true
+        });
+        for (tx_hash, claim) in newly_confirmed {
+            self.confirmed.insert(tx_hash, claim);
+        }
+        resolved
+    }
+
+    /// Re-arms a confirmed claim after the micro-block that confirmed it
+    /// was rolled back (e.g. by a view change), so it resumes watching for
+    /// confirmation instead of staying stuck at a stale `Confirmed` status.
+    pub fn rollback(&mut self, tx_hash: &Hash) -> bool {
+        match self.confirmed.remove(tx_hash) {
+            Some(claim) => {
+                self.pending.insert(*tx_hash, claim);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Prunes every pending claim old enough that it will never resolve,
+    /// returning each one paired with [`ClaimStatus::Pruned`].
+    pub fn expire(&mut self, current_epoch: u64) -> Vec<(Hash, ClaimStatus)> {
+        let expired: Vec<Hash> = self
+            .pending
+            .iter()
+            .filter(|(_, claim)| {
+                current_epoch >= claim.registered_epoch + claim.expires_after_epochs
+            })
+            .map(|(tx_hash, _)| *tx_hash)
+            .collect();
+        for tx_hash in &expired {
+            self.pending.remove(tx_hash);
+        }
+        expired
+            .into_iter()
+            .map(|tx_hash| (tx_hash, ClaimStatus::Pruned))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(name: &str) -> Hash {
+        Hash::digest(&name.to_string())
+    }
+
+    #[test]
+    fn confirms_once_every_claimed_output_is_produced() {
+        let mut tracker = ClaimTracker::new();
+        let tx = h("tx-1");
+        tracker.register(tx, vec![h("out-1"), h("out-2")], vec![h("in-1")], 0, 10);
+
+        let resolved = tracker.observe_block(0, 1, &[h("out-1")], &[]);
+        assert!(resolved.is_empty(), "only one of two outputs landed");
+
+        let resolved = tracker.observe_block(0, 2, &[h("out-1"), h("out-2")], &[]);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].0, tx);
+        assert_eq!(
+            resolved[0].1,
+            ClaimStatus::Confirmed { epoch: 0, offset: 2 }
+        );
+    }
+
+    #[test]
+    fn conflicts_when_a_claimed_input_is_spent_by_someone_else_first() {
+        let mut tracker = ClaimTracker::new();
+        let tx = h("tx-2");
+        tracker.register(tx, vec![h("out-1")], vec![h("in-1")], 0, 10);
+
+        let resolved = tracker.observe_block(0, 1, &[h("some-other-output")], &[h("in-1")]);
+        assert_eq!(resolved, vec![(tx, ClaimStatus::Conflicted)]);
+    }
+
+    #[test]
+    fn a_resolved_claim_converts_into_a_connection_response() {
+        use crate::ConnectionResponse;
+
+        let mut tracker = ClaimTracker::new();
+        let tx = h("tx-5");
+        tracker.register(tx, vec![h("out-1")], vec![h("in-1")], 0, 10);
+        let resolved = tracker.observe_block(0, 1, &[h("out-1")], &[]);
+
+        let responses: Vec<ConnectionResponse> =
+            resolved.into_iter().map(ConnectionResponse::from).collect();
+        assert_eq!(
+            responses,
+            vec![ConnectionResponse::ClaimResolved {
+                tx_hash: tx,
+                status: ClaimStatus::Confirmed { epoch: 0, offset: 1 },
+            }]
+        );
+    }
+
+    #[test]
+    fn rollback_re_arms_a_confirmed_claim() {
+        let mut tracker = ClaimTracker::new();
+        let tx = h("tx-3");
+        tracker.register(tx, vec![h("out-1")], vec![h("in-1")], 0, 10);
+        tracker.observe_block(0, 1, &[h("out-1")], &[]);
+
+        assert!(tracker.rollback(&tx));
+        // Re-armed: the next block can confirm it again.
+        let resolved = tracker.observe_block(0, 2, &[h("out-1")], &[]);
+        assert_eq!(resolved, vec![(tx, ClaimStatus::Confirmed { epoch: 0, offset: 2 })]);
+    }
+
+    #[test]
+    fn expires_claims_that_outlive_their_budget() {
+        let mut tracker = ClaimTracker::new();
+        let tx = h("tx-4");
+        tracker.register(tx, vec![h("out-1")], vec![h("in-1")], 0, 3);
+
+        assert!(tracker.expire(2).is_empty(), "not old enough yet");
+        assert_eq!(tracker.expire(3), vec![(tx, ClaimStatus::Pruned)]);
+        // Once expired, a later block observation no longer resolves it.
+        assert!(tracker.observe_block(5, 0, &[h("out-1")], &[]).is_empty());
+    }
+}
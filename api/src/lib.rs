@@ -23,17 +23,37 @@
 
 #![deny(warnings)]
 
+mod acme;
+mod chunked;
 mod config;
 mod crypto;
 mod error;
+mod eventuality;
+mod output_index;
+mod replay;
 mod server;
+mod signing;
 
+pub use crate::acme::{
+    finalize_order, key_authorization, new_order, should_renew, AcmeAccount, AcmeDirectory, Jws,
+};
+pub use crate::chunked::{Reassembler, FRAME_SIZE};
 pub use crate::config::load_api_token;
+pub use crate::eventuality::{ClaimStatus, ClaimTracker};
 pub use crate::config::ApiConfig;
 pub use crate::crypto::ApiToken;
 pub use crate::error::KeyError;
+pub use crate::output_index::{OutputIndex, OutputRecord};
+pub use crate::replay::{Evicted, ReplayBuffer, Sequenced};
 pub use crate::server::WebSocketServer;
-pub use stegos_node::{EpochChanged, NodeRequest, NodeResponse, SyncChanged};
+pub use crate::signing::{sign_request, SignedAuth, SignedEnvelope, SigningError};
+// `output.rs`, where `Output` is actually defined, isn't part of this
+// checkout (see `blockchain/src/lib.rs`), so this re-export doesn't resolve
+// to anything buildable yet; `crate::output_index::OutputIndex` carries the
+// real created/spent/epoch tracking a `GetOutput` handler needs, generic
+// over the payload type so it doesn't depend on `Output` existing here.
+pub use stegos_blockchain::Output;
+pub use stegos_node::{EpochChanged, NodeRequest, NodeResponse, SyncChanged, TransactionStatus};
 pub use stegos_wallet::{WalletNotification, WalletRequest, WalletResponse};
 pub use websocket::WebSocketError;
 
@@ -42,12 +62,84 @@ use log::*;
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
 use serde_derive::{Deserialize, Serialize};
+use stegos_crypto::hash::Hash;
 use stegos_crypto::pbc;
 
-pub type RequestId = u64;
+/// A JSON-RPC 2.0 id: either an integer or a string. Accepting both lets
+/// standard JSON-RPC clients use whichever id scheme they prefer; `Int(0)`
+/// is the default and marks a notification with no reply expected, matching
+/// the old bare-`u64` behavior.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum JsonId {
+    Int(u64),
+    Str(String),
+}
 
-fn is_request_id_default(id: &RequestId) -> bool {
-    *id == 0
+impl Default for JsonId {
+    fn default() -> Self {
+        JsonId::Int(0)
+    }
+}
+
+/// A structured JSON-RPC 2.0 error object, carried instead of a bare string.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+/// The literal JSON-RPC 2.0 version marker, `"2.0"`.
+pub const JSONRPC_VERSION: &str = "2.0";
+
+fn is_request_id_default(id: &JsonId) -> bool {
+    *id == JsonId::default()
+}
+
+/// A server-side filter evaluated before a message is delivered to a
+/// subscription, so a single connection can multiplex several overlapping
+/// subscriptions and each only gets what it asked for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionFilter {
+    /// Deliver everything on the topic.
+    None,
+    /// Unicast only: match senders whose public key starts with this byte prefix.
+    SenderPrefix(Vec<u8>),
+    /// Broadcast only: match topics against a `*`-glob.
+    TopicGlob(String),
+}
+
+impl SubscriptionFilter {
+    /// Whether a unicast message from `from` should be delivered to a
+    /// subscription carrying this filter.
+    pub fn matches_unicast(&self, from: &pbc::PublicKey) -> bool {
+        match self {
+            SubscriptionFilter::None => true,
+            SubscriptionFilter::SenderPrefix(prefix) => from.to_bytes().starts_with(prefix),
+            SubscriptionFilter::TopicGlob(_) => false,
+        }
+    }
+
+    /// Whether a broadcast on `topic` should be delivered to a subscription
+    /// carrying this filter. The glob only supports a single trailing `*`,
+    /// which covers the common "everything under this prefix" case.
+    pub fn matches_broadcast(&self, topic: &str) -> bool {
+        match self {
+            SubscriptionFilter::None => true,
+            SubscriptionFilter::TopicGlob(glob) => match glob.strip_suffix('*') {
+                Some(prefix) => topic.starts_with(prefix),
+                None => topic == glob,
+            },
+            SubscriptionFilter::SenderPrefix(_) => false,
+        }
+    }
+
+    fn none() -> Self {
+        SubscriptionFilter::None
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,16 +147,27 @@ fn is_request_id_default(id: &RequestId) -> bool {
 #[serde(rename_all = "snake_case")]
 pub enum NetworkRequest {
     SubscribeUnicast {
+        /// Client-assigned id identifying this subscription among any
+        /// others on the same connection; echoed back on every matching
+        /// notification.
+        sub_id: u64,
         topic: String,
+        #[serde(default = "SubscriptionFilter::none")]
+        filter: SubscriptionFilter,
     },
     SubscribeBroadcast {
+        sub_id: u64,
         topic: String,
+        #[serde(default = "SubscriptionFilter::none")]
+        filter: SubscriptionFilter,
     },
+    /// Tears down one subscription by id, leaving any other subscription on
+    /// this connection (even on the same topic) untouched.
     UnsubscribeUnicast {
-        topic: String,
+        sub_id: u64,
     },
     UnsubscribeBroadcast {
-        topic: String,
+        sub_id: u64,
     },
     SendUnicast {
         topic: String,
@@ -75,6 +178,40 @@ pub enum NetworkRequest {
         topic: String,
         data: Vec<u8>,
     },
+    /// First frame of a chunked unicast transfer: `chunk` is frame `0` of
+    /// `total_len` plaintext bytes split per [`crate::chunked::FRAME_SIZE`].
+    SendUnicastStart {
+        transfer_id: u64,
+        topic: String,
+        to: pbc::PublicKey,
+        total_len: usize,
+        chunk: Vec<u8>,
+    },
+    SendUnicastContinue {
+        transfer_id: u64,
+        seq: u64,
+        chunk: Vec<u8>,
+    },
+    /// Marks a chunked unicast transfer complete; the receiving side
+    /// delivers the reassembled payload as a single `UnicastMessage` once
+    /// every frame has arrived.
+    SendUnicastFinish {
+        transfer_id: u64,
+    },
+    PublishBroadcastStart {
+        transfer_id: u64,
+        topic: String,
+        total_len: usize,
+        chunk: Vec<u8>,
+    },
+    PublishBroadcastContinue {
+        transfer_id: u64,
+        seq: u64,
+        chunk: Vec<u8>,
+    },
+    PublishBroadcastFinish {
+        transfer_id: u64,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,7 +224,12 @@ pub enum NetworkResponse {
     UnsubscribedBroadcast,
     SentUnicast,
     PublishedBroadcast,
-    Error { error: String },
+    /// Acknowledges one frame of a chunked transfer, confirming it was
+    /// buffered so the sender can pace further frames (backpressure).
+    FrameReceived {
+        transfer_id: u64,
+    },
+    Error(JsonRpcError),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,14 +237,60 @@ pub enum NetworkResponse {
 #[serde(rename_all = "snake_case")]
 pub enum NetworkNotification {
     UnicastMessage {
+        /// Every subscription on this connection whose filter matched.
+        sub_ids: Vec<u64>,
         topic: String,
         from: pbc::PublicKey,
         data: Vec<u8>,
     },
     BroadcastMessage {
+        sub_ids: Vec<u64>,
         topic: String,
         data: Vec<u8>,
     },
+    /// Progress of an in-flight chunked transfer, emitted as each frame is
+    /// received so the receiving client can show progress without waiting
+    /// for the whole payload.
+    TransferProgress {
+        transfer_id: u64,
+        bytes_received: usize,
+        total_len: usize,
+    },
+}
+
+/// Connection-level requests that aren't addressed to the node, wallet or
+/// network handlers, but to the WebSocket connection itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "request")]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionRequest {
+    /// Replay every notification sent after `since_seq` on this connection's
+    /// previous incarnation, before resuming the live stream.
+    Resend { since_seq: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "notification")]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionResponse {
+    Resent { replayed: u64 },
+    /// `since_seq` has already fallen out of the replay buffer; the client
+    /// must fall back to a full resync instead of trusting a partial replay.
+    ReplayUnavailable { oldest_available_seq: u64 },
+    /// A `SubscribeTransaction` claim resolved by
+    /// [`crate::eventuality::ClaimTracker::observe_block`] or
+    /// [`crate::eventuality::ClaimTracker::expire`], serialized the same
+    /// way any other connection-level notification is.
+    ClaimResolved {
+        tx_hash: Hash,
+        status: ClaimStatus,
+    },
+}
+
+impl From<(Hash, ClaimStatus)> for ConnectionResponse {
+    fn from((tx_hash, status): (Hash, ClaimStatus)) -> ConnectionResponse {
+        ConnectionResponse::ClaimResolved { tx_hash, status }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -111,6 +299,7 @@ pub enum RequestKind {
     NetworkRequest(NetworkRequest),
     WalletRequest(WalletRequest),
     NodeRequest(NodeRequest),
+    ConnectionRequest(ConnectionRequest),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -118,9 +307,14 @@ pub enum RequestKind {
 pub struct Request {
     #[serde(flatten)]
     pub kind: RequestKind,
+    /// Present and equal to `"2.0"` when the client opted into compliant
+    /// JSON-RPC 2.0 framing; `None` keeps the legacy envelope.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jsonrpc: Option<String>,
     #[serde(default)]
     #[serde(skip_serializing_if = "is_request_id_default")]
-    pub id: u64,
+    pub id: JsonId,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -129,6 +323,7 @@ pub struct Request {
 pub enum NodeNotification {
     SyncChanged(SyncChanged),
     EpochChanged(EpochChanged),
+    TransactionStatus(TransactionStatus),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -140,6 +335,7 @@ pub enum ResponseKind {
     WalletNotification(WalletNotification),
     NodeResponse(NodeResponse),
     NodeNotification(NodeNotification),
+    ConnectionResponse(ConnectionResponse),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -147,9 +343,20 @@ pub enum ResponseKind {
 pub struct Response {
     #[serde(flatten)]
     pub kind: ResponseKind,
+    /// Echoes `Request::jsonrpc` when that request opted into JSON-RPC 2.0
+    /// framing.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jsonrpc: Option<String>,
     #[serde(default)]
     #[serde(skip_serializing_if = "is_request_id_default")]
-    pub id: RequestId,
+    pub id: JsonId,
+    /// The replay-buffer sequence number this notification was stamped
+    /// with. `None` for request/response pairs; always `Some` for
+    /// notifications, so a reconnecting client can `Resend` from it.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
 }
 
 pub fn encode<T: Serialize>(api_token: &ApiToken, msg: &T) -> String {
@@ -181,3 +388,17 @@ pub fn decode<T: DeserializeOwned>(api_token: &ApiToken, msg: &str) -> Result<T,
     };
     Ok(msg)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_resolved_claim_serializes_as_a_connection_notification() {
+        let tx_hash = Hash::digest(&"tx-1".to_string());
+        let response: ConnectionResponse = (tx_hash, ClaimStatus::Conflicted).into();
+        let json = serde_json::to_value(&response).expect("serializable");
+        assert_eq!(json["notification"], "claim_resolved");
+        assert_eq!(json["status"], "conflicted");
+    }
+}
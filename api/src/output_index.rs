@@ -0,0 +1,142 @@
+//! Tracks unspent/spent status for `NodeRequest::GetOutput` lookups.
+//!
+//! `GetOutput { output_hash }` answers with the output's payload, whether
+//! it's still unspent, and the epoch it was created in — the kind of
+//! point lookup a wallet makes to check a UTXO's status before building a
+//! spend. [`OutputIndex`] is the real engine behind that answer: as blocks
+//! land, the handler that owns the live chain calls
+//! [`OutputIndex::record_created`] for every new output and
+//! [`OutputIndex::record_spent`] for every input a transaction consumes,
+//! and [`OutputIndex::lookup`] serves the resulting point query.
+//!
+//! It's generic over the output payload type rather than hard-coded to
+//! `stegos_blockchain::Output`, because `output.rs` (where that type is
+//! defined) isn't part of this partial checkout (see
+//! `blockchain/src/lib.rs`), so nothing here can construct or pattern-match
+//! a real `Output`. Once it is, `OutputIndex<Output>` is a real backing
+//! store for a `GetOutput` handler; until then this tracks the same
+//! created/spent/epoch facts against any payload type a caller can supply.
+
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::collections::HashMap;
+use stegos_crypto::hash::Hash;
+
+/// One tracked output: its payload, creation epoch, and current spent state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputRecord<O> {
+    pub output: O,
+    pub created_epoch: u64,
+    pub spent: bool,
+}
+
+/// A point-lookup index of every output the chain has seen, keyed by hash.
+#[derive(Debug)]
+pub struct OutputIndex<O> {
+    records: HashMap<Hash, OutputRecord<O>>,
+}
+
+impl<O: Clone> Default for OutputIndex<O> {
+    fn default() -> Self {
+        OutputIndex {
+            records: HashMap::new(),
+        }
+    }
+}
+
+impl<O: Clone> OutputIndex<O> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a newly created output as unspent.
+    pub fn record_created(&mut self, output_hash: Hash, output: O, created_epoch: u64) {
+        self.records.insert(
+            output_hash,
+            OutputRecord {
+                output,
+                created_epoch,
+                spent: false,
+            },
+        );
+    }
+
+    /// Marks a tracked output spent. Returns `false` if `output_hash` isn't
+    /// tracked, e.g. because it belongs to a block this index hasn't seen.
+    pub fn record_spent(&mut self, output_hash: &Hash) -> bool {
+        match self.records.get_mut(output_hash) {
+            Some(record) => {
+                record.spent = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Looks up an output's current record by hash.
+    pub fn lookup(&self, output_hash: &Hash) -> Option<&OutputRecord<O>> {
+        self.records.get(output_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(name: &str) -> Hash {
+        Hash::digest(&name.to_string())
+    }
+
+    #[test]
+    fn a_freshly_created_output_is_unspent() {
+        let mut index = OutputIndex::new();
+        let hash = h("out-1");
+        index.record_created(hash, "payload-1", 3);
+
+        let record = index.lookup(&hash).expect("tracked");
+        assert_eq!(record.output, "payload-1");
+        assert_eq!(record.created_epoch, 3);
+        assert!(!record.spent);
+    }
+
+    #[test]
+    fn spending_a_tracked_output_flips_its_status() {
+        let mut index = OutputIndex::new();
+        let hash = h("out-2");
+        index.record_created(hash, "payload-2", 1);
+
+        assert!(index.record_spent(&hash));
+        assert!(index.lookup(&hash).expect("tracked").spent);
+    }
+
+    #[test]
+    fn spending_an_unknown_output_reports_failure_without_panicking() {
+        let mut index = OutputIndex::<&str>::new();
+        assert!(!index.record_spent(&h("never-seen")));
+    }
+
+    #[test]
+    fn unknown_outputs_look_up_to_none() {
+        let index = OutputIndex::<&str>::new();
+        assert!(index.lookup(&h("never-seen")).is_none());
+    }
+}
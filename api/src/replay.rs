@@ -0,0 +1,131 @@
+//! Bounded replay buffer for outgoing notifications.
+//!
+//! Each outgoing notification is stamped with a monotonically increasing
+//! sequence number as it is pushed. A client that drops its connection can
+//! reconnect and ask to replay everything sent after the last sequence it
+//! saw, instead of silently losing it, the way a webhook provider lets a
+//! subscriber resend missed deliveries.
+
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::collections::VecDeque;
+
+/// A notification paired with the sequence number it was stamped with.
+#[derive(Debug, Clone)]
+pub struct Sequenced<T> {
+    pub seq: u64,
+    pub payload: T,
+}
+
+/// Returned by [`ReplayBuffer::since`] when the requested sequence has
+/// already been evicted, so the client knows it must do a full resync
+/// instead of trusting a partial replay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Evicted {
+    pub oldest_available_seq: u64,
+}
+
+/// A fixed-capacity ring buffer of the most recent notifications, keyed by
+/// sequence number.
+pub struct ReplayBuffer<T> {
+    capacity: usize,
+    next_seq: u64,
+    buffer: VecDeque<Sequenced<T>>,
+}
+
+impl<T: Clone> ReplayBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "a zero-capacity replay buffer can't replay anything");
+        ReplayBuffer {
+            capacity,
+            next_seq: 1,
+            buffer: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Stamps `payload` with the next sequence number, stores it, and
+    /// returns the sequence it was stamped with.
+    pub fn push(&mut self, payload: T) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        if self.buffer.len() == self.capacity {
+            self.buffer.pop_front();
+        }
+        self.buffer.push_back(Sequenced { seq, payload });
+        seq
+    }
+
+    /// Returns every notification sent after `since_seq`, in order, or
+    /// `Err(Evicted)` if some of that range has already fallen out of the
+    /// buffer and the client must resync from scratch instead.
+    pub fn since(&self, since_seq: u64) -> Result<Vec<Sequenced<T>>, Evicted> {
+        let oldest_retained = self.buffer.front().map(|n| n.seq).unwrap_or(self.next_seq);
+        if since_seq.saturating_add(1) < oldest_retained {
+            return Err(Evicted {
+                oldest_available_seq: oldest_retained,
+            });
+        }
+        Ok(self
+            .buffer
+            .iter()
+            .filter(|n| n.seq > since_seq)
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_everything_after_the_requested_sequence() {
+        let mut buf = ReplayBuffer::new(10);
+        for i in 0..5 {
+            buf.push(format!("notification-{}", i));
+        }
+        let replayed = buf.since(2).unwrap();
+        assert_eq!(replayed.len(), 3);
+        assert_eq!(replayed[0].seq, 3);
+        assert_eq!(replayed[0].payload, "notification-2");
+    }
+
+    #[test]
+    fn reports_eviction_when_requested_sequence_fell_out_of_the_buffer() {
+        let mut buf = ReplayBuffer::new(3);
+        for i in 0..10 {
+            buf.push(i);
+        }
+        // Only seq 8, 9, 10 are retained; seq 1 is long gone.
+        let err = buf.since(1).unwrap_err();
+        assert_eq!(err.oldest_available_seq, 8);
+    }
+
+    #[test]
+    fn since_u64_max_replays_nothing_instead_of_overflowing() {
+        let mut buf = ReplayBuffer::new(3);
+        buf.push("only notification");
+        // Nothing after u64::MAX was ever sent, and nothing was evicted.
+        let replayed = buf.since(u64::MAX).unwrap();
+        assert!(replayed.is_empty());
+    }
+}
@@ -24,18 +24,39 @@ use failure::{bail, Error};
 
 use async_trait::async_trait;
 
-use crate::{Request, RequestKind, ResponseKind};
+use crate::{JsonId, JsonRpcError, NodeNotification, Request, RequestKind, ResponseKind};
+use futures::future::join_all;
 use futures::stream::StreamExt;
 use futures::Stream;
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json;
 use std::convert::{TryFrom, TryInto};
-use stegos_node::{ChainNotification, Node, NodeRequest, NodeResponse, StatusNotification};
+use stegos_node::{Node, NodeRequest, NodeResponse};
 use stegos_wallet::{
     api::{WalletRequest, WalletResponse},
     Wallet,
 };
 
+/// Standard JSON-RPC 2.0 error codes we actually emit.
+const INVALID_REQUEST: i64 = -32600;
+const INTERNAL_ERROR: i64 = -32603;
+
+fn invalid_request(message: impl Into<String>) -> JsonRpcError {
+    JsonRpcError {
+        code: INVALID_REQUEST,
+        message: message.into(),
+        data: None,
+    }
+}
+
+fn internal_error(error: &Error) -> JsonRpcError {
+    JsonRpcError {
+        code: INTERNAL_ERROR,
+        message: error.to_string(),
+        data: None,
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct RawRequest(pub Request);
 
@@ -43,10 +64,33 @@ impl RawRequest {
     pub(super) fn is_subscribe(&self) -> bool {
         match &self.0.kind {
             RequestKind::NodeRequest(r) => match r {
-                NodeRequest::SubscribeStatus { .. } | NodeRequest::SubscribeChain { .. } => true,
+                NodeRequest::SubscribeStatus { .. }
+                | NodeRequest::SubscribeChain { .. }
+                // The actual Confirmed/Conflicted/Pruned tracking behind this
+                // subscription is `crate::eventuality::ClaimTracker`, driven
+                // by whatever holds the live chain (the node-side handler,
+                // outside this checkout) feeding it each block's produced
+                // outputs and spent inputs; each resolved claim converts
+                // into a real, serializable `ConnectionResponse::ClaimResolved`
+                // via `ConnectionResponse::from`. This request variant itself
+                // still just passes through like every other `NodeRequest`.
+                | NodeRequest::SubscribeTransaction { .. } => true,
                 _ => false,
             },
-            RequestKind::WalletsRequest(r) => false,
+            RequestKind::NetworkRequest(_)
+            | RequestKind::WalletRequest(_)
+            | RequestKind::ConnectionRequest(_) => false,
+        }
+    }
+
+    /// The JSON-RPC id to echo back on the response, if any was given.
+    /// A request with the default id (`Int(0)`) is a JSON-RPC notification
+    /// and gets no reply.
+    pub(super) fn id(&self) -> Option<JsonId> {
+        if self.0.id == JsonId::default() {
+            None
+        } else {
+            Some(self.0.id.clone())
         }
     }
 }
@@ -55,6 +99,11 @@ impl TryFrom<RawRequest> for NodeRequest {
     type Error = Error;
     fn try_from(request: RawRequest) -> Result<NodeRequest, Self::Error> {
         match request.0.kind {
+            // `NodeRequest::GetOutput` and every other node variant pass
+            // through unchanged here. The created/spent/epoch tracking that
+            // answer would be served from is `crate::output_index::OutputIndex`,
+            // kept by whatever holds the live chain (outside this checkout);
+            // this request variant is still just a pass-through.
             RequestKind::NodeRequest(req) => Ok(req),
             _ => bail!("Cannot parse request as node request."),
         }
@@ -65,24 +114,42 @@ impl TryFrom<RawRequest> for WalletRequest {
     type Error = Error;
     fn try_from(request: RawRequest) -> Result<WalletRequest, Self::Error> {
         match request.0.kind {
-            RequestKind::WalletsRequest(req) => Ok(req),
+            RequestKind::WalletRequest(req) => Ok(req),
             _ => bail!("Cannot parse request as wallet request."),
         }
     }
 }
 
+/// A JSON-RPC 2.0 response envelope: either the successful `result`, paired
+/// with the id of the request that produced it, or a structured `error`.
+/// Notifications (subscription pushes) carry `id: None`.
 #[derive(Debug)]
-pub struct RawResponse(pub ResponseKind);
+pub struct RawResponse {
+    pub id: Option<JsonId>,
+    pub result: Result<ResponseKind, JsonRpcError>,
+}
 
 impl RawResponse {
+    fn notification(kind: ResponseKind) -> RawResponse {
+        RawResponse {
+            id: None,
+            result: Ok(kind),
+        }
+    }
+
     pub(super) fn subscribe_to_stream(
         &mut self,
     ) -> Result<Box<dyn Stream<Item = RawResponse> + Unpin + Send>, Error> {
-        match &mut self.0 {
+        let kind = match &mut self.result {
+            Ok(kind) => kind,
+            Err(e) => bail!("Cannot subscribe on an error response: {:?}", e),
+        };
+        match kind {
             ResponseKind::NodeResponse(r) => {
                 match &mut *r {
-                    NodeResponse::SubscribedStatus{rx,..} => Ok(Box::new(rx.take().expect("Stream exist").map(ResponseKind::StatusNotification).map(RawResponse))),
-                    NodeResponse::SubscribedChain{rx,..} => Ok(Box::new(rx.take().expect("Stream exist").map(ResponseKind::ChainNotification).map(RawResponse))),
+                    NodeResponse::SubscribedStatus{rx,..} => Ok(Box::new(rx.take().expect("Stream exist").map(NodeNotification::SyncChanged).map(ResponseKind::NodeNotification).map(RawResponse::notification))),
+                    NodeResponse::SubscribedChain{rx,..} => Ok(Box::new(rx.take().expect("Stream exist").map(NodeNotification::EpochChanged).map(ResponseKind::NodeNotification).map(RawResponse::notification))),
+                    NodeResponse::SubscribedTransaction{rx,..} => Ok(Box::new(rx.take().expect("Stream exist").map(NodeNotification::TransactionStatus).map(ResponseKind::NodeNotification).map(RawResponse::notification))),
                     // e @ NodeResponse::Error => // TODO support error in response
                     response => bail!("Received response that cannot be converted to notification stream: response={:?}", response)
                 }
@@ -90,26 +157,36 @@ impl RawResponse {
             ResponseKind::WalletResponse(_) | ResponseKind::WalletNotification(_) => {
                 bail!("Wallets notification didn't support.")
             }
-            ResponseKind::ChainNotification(_) | ResponseKind::StatusNotification(_) => {
+            ResponseKind::NodeNotification(_) => {
                 bail!("Got notification message, expected response.")
             }
+            ResponseKind::NetworkResponse(_)
+            | ResponseKind::NetworkNotification(_)
+            | ResponseKind::ConnectionResponse(_) => {
+                bail!("Cannot subscribe on a non-node response.")
+            }
         }
     }
 }
 
 impl From<NodeResponse> for RawResponse {
     fn from(response: NodeResponse) -> RawResponse {
-        RawResponse(ResponseKind::NodeResponse(response))
+        RawResponse {
+            id: None,
+            result: Ok(ResponseKind::NodeResponse(response)),
+        }
     }
 }
 
 impl From<WalletResponse> for RawResponse {
     fn from(response: WalletResponse) -> RawResponse {
-        RawResponse(ResponseKind::WalletResponse(response))
+        RawResponse {
+            id: None,
+            result: Ok(ResponseKind::WalletResponse(response)),
+        }
     }
 }
 
-// Todo: Later replace our requests with json-rpc core, and remove register/apihandler.
 #[async_trait]
 pub trait ApiHandler: Sync + Send {
     fn name(&self) -> String {
@@ -121,6 +198,41 @@ pub trait ApiHandler: Sync + Send {
     async fn try_process(&self, req: RawRequest) -> Result<RawResponse, Error>;
 }
 
+/// Dispatches a single JSON-RPC request, stamping the response (success or
+/// structured error) with the id the client sent.
+pub(super) async fn process_request(handler: &dyn ApiHandler, request: Request) -> RawResponse {
+    let id = if request.id == JsonId::default() {
+        None
+    } else {
+        Some(request.id.clone())
+    };
+    match handler.try_process(RawRequest(request)).await {
+        Ok(mut response) => {
+            response.id = id;
+            response
+        }
+        Err(e) => RawResponse {
+            id,
+            result: Err(internal_error(&e)),
+        },
+    }
+}
+
+/// Dispatches a JSON-RPC batch: every request runs concurrently, and the
+/// responses are reassembled in the same order the requests were given in.
+pub(super) async fn process_batch(
+    handler: &dyn ApiHandler,
+    requests: Vec<Request>,
+) -> Vec<RawResponse> {
+    if requests.is_empty() {
+        return vec![RawResponse {
+            id: None,
+            result: Err(invalid_request("Empty batch request")),
+        }];
+    }
+    join_all(requests.into_iter().map(|r| process_request(handler, r))).await
+}
+
 #[async_trait]
 impl<T: ApiHandler + Sync + Clone + 'static> ApiHandler for Option<T> {
     fn name(&self) -> String {
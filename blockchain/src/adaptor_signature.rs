@@ -0,0 +1,118 @@
+//! Adaptor (a.k.a. "encrypted") Schnorr signatures over curve1174.
+//!
+//! An adaptor signature is bound to a statement point `Y = y*G` without
+//! revealing the witness `y`. Whoever later completes it into a valid
+//! signature publishes `y` in the clear, which lets a counterparty unlock a
+//! mirror transaction on another chain. This is the building block behind
+//! the hash-time-locked swap outputs in [`crate::htlc`].
+
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use failure::Fail;
+use stegos_crypto::curve1174::{Fr, Pt, PublicKey, SchnorrSig, SecretKey, G};
+use stegos_crypto::hash::Hash;
+
+/// An adaptor signature bound to the statement point `y`. It verifies
+/// against `pkey`/`hash` like a normal Schnorr signature challenge, but the
+/// response `s` is short by the witness `y = y_scalar*G` until someone who
+/// knows `y_scalar` completes it with [`complete_adaptor_signature`].
+#[derive(Clone, Debug)]
+pub struct AdaptorSignature {
+    /// Public nonce commitment `k*G`.
+    pub k: Pt,
+    /// Encrypted response, missing the witness scalar.
+    pub s: Fr,
+    /// Statement point the signature is bound to.
+    pub y: Pt,
+}
+
+#[derive(Debug, Fail)]
+pub enum AdaptorSignatureError {
+    #[fail(display = "adaptor signature does not verify against its statement point")]
+    InvalidAdaptorSignature,
+    #[fail(display = "completed signature does not verify")]
+    InvalidCompletedSignature,
+}
+
+/// Produces an adaptor signature over `hash`, encrypted under the statement
+/// point `y = y_scalar*G`. The caller only needs `y`; `y_scalar` stays with
+/// whoever will eventually complete the signature.
+pub fn create_adaptor_signature(skey: &SecretKey, y: Pt, hash: &Hash) -> AdaptorSignature {
+    let nonce = Fr::random();
+    let k = G * nonce;
+    let e = Fr::from(Hash::digest(&(k + y, hash)));
+    let s = nonce - e * Fr::from(skey);
+    AdaptorSignature { k, s, y }
+}
+
+/// Checks that `sig` is a well-formed adaptor signature for `pkey`/`hash`,
+/// i.e. that completing it with the witness behind `sig.y` would yield a
+/// valid Schnorr signature.
+pub fn verify_adaptor_signature(
+    pkey: &PublicKey,
+    hash: &Hash,
+    sig: &AdaptorSignature,
+) -> Result<(), AdaptorSignatureError> {
+    let e = Fr::from(Hash::digest(&(sig.k + sig.y, hash)));
+    let lhs = G * sig.s + Pt::from(*pkey) * e;
+    if lhs == sig.k {
+        Ok(())
+    } else {
+        Err(AdaptorSignatureError::InvalidAdaptorSignature)
+    }
+}
+
+/// Completes an adaptor signature into a regular, verifiable Schnorr
+/// signature using the witness scalar behind the statement point.
+pub fn complete_adaptor_signature(sig: &AdaptorSignature, witness: &Fr) -> SchnorrSig {
+    SchnorrSig {
+        u: sig.s + *witness,
+        K: sig.k + sig.y,
+    }
+}
+
+/// Recovers the witness scalar `y` from a completed signature and the
+/// adaptor signature it was completed from: `y = s_completed - s_adaptor`.
+pub fn recover_witness(sig: &AdaptorSignature, completed: &SchnorrSig) -> Fr {
+    completed.u - sig.s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stegos_crypto::curve1174::make_random_keys;
+
+    #[test]
+    fn adaptor_signature_completes_and_recovers_witness() {
+        let (skey, pkey) = make_random_keys();
+        let y_scalar = Fr::random();
+        let y = G * y_scalar;
+        let hash = Hash::digest("atomic swap");
+
+        let adaptor = create_adaptor_signature(&skey, y, &hash);
+        verify_adaptor_signature(&pkey, &hash, &adaptor).expect("adaptor signature is valid");
+
+        let completed = complete_adaptor_signature(&adaptor, &y_scalar);
+        let recovered = recover_witness(&adaptor, &completed);
+        assert_eq!(recovered, y_scalar);
+    }
+}
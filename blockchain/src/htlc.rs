@@ -0,0 +1,147 @@
+//! Hash-time-locked outputs for trustless cross-chain swaps.
+//!
+//! An `HtlcOutput` is spendable two ways: by the recipient, who presents a
+//! preimage `x` with `H(x) == hash_lock`, at any height/epoch before
+//! `timelock`; or, once `timelock` is reached, refunded back to the sender. Combined
+//! with [`crate::adaptor_signature`], the recipient spend is signed with an
+//! adaptor signature so that completing it publishes the witness needed to
+//! unlock the mirror transaction on the other chain.
+
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use failure::Fail;
+use serde_derive::{Deserialize, Serialize};
+use stegos_crypto::curve1174::PublicKey;
+use stegos_crypto::hash::Hash;
+
+/// A locked payment, refundable to `sender` after `timelock` or claimable by
+/// `recipient` on presentation of a preimage of `hash_lock`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HtlcOutput {
+    /// Recipient of the claim spend.
+    pub recipient: PublicKey,
+    /// Sender, who can reclaim the funds after `timelock`.
+    pub sender: PublicKey,
+    /// `H(x)` that a claim spend must reveal the preimage of.
+    pub hash_lock: Hash,
+    /// Epoch from which the sender's refund path becomes valid and the
+    /// recipient's claim path stops being valid.
+    pub timelock: u64,
+    /// Locked amount.
+    pub amount: i64,
+}
+
+#[derive(Debug, Fail)]
+pub enum HtlcError {
+    #[fail(display = "preimage does not hash to the committed hash lock")]
+    InvalidPreimage,
+    #[fail(display = "refund attempted before timelock epoch {}", _0)]
+    TimelockNotExpired(u64),
+    #[fail(display = "claim attempted at or after timelock epoch {}", _0)]
+    TimelockExpired(u64),
+}
+
+impl HtlcOutput {
+    pub fn new(
+        recipient: &PublicKey,
+        sender: &PublicKey,
+        hash_lock: Hash,
+        timelock: u64,
+        amount: i64,
+    ) -> Self {
+        HtlcOutput {
+            recipient: *recipient,
+            sender: *sender,
+            hash_lock,
+            timelock,
+            amount,
+        }
+    }
+
+    /// Validates a claim spend: the chain must not have reached `timelock`
+    /// yet, and the preimage must hash to `hash_lock`.
+    pub fn validate_claim(&self, preimage: &[u8], current_epoch: u64) -> Result<(), HtlcError> {
+        if current_epoch >= self.timelock {
+            return Err(HtlcError::TimelockExpired(self.timelock));
+        }
+        if Hash::digest(&preimage) == self.hash_lock {
+            Ok(())
+        } else {
+            Err(HtlcError::InvalidPreimage)
+        }
+    }
+
+    /// Validates a refund spend: the chain must have passed `timelock`.
+    pub fn validate_refund(&self, current_epoch: u64) -> Result<(), HtlcError> {
+        if current_epoch >= self.timelock {
+            Ok(())
+        } else {
+            Err(HtlcError::TimelockNotExpired(self.timelock))
+        }
+    }
+}
+
+// `HtlcOutput` is designed to become a fourth `Output` variant (alongside
+// `PaymentOutput`/`PublicPaymentOutput`/`StakeOutput`) in `output.rs`, and to
+// gain one match arm in `Blockchain`'s input-spending validation dispatching
+// to `validate_claim`/`validate_refund` depending on which spend path the
+// transaction exercises — the same shape as the existing signature check for
+// the other variants. Neither `output.rs` nor `blockchain.rs` are part of
+// this partial checkout (see `lib.rs`), so that match arm can't be added
+// here; `validate_claim`/`validate_refund` are written to be the exact
+// functions that arm would call.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stegos_crypto::curve1174::make_random_keys;
+
+    fn keys() -> (PublicKey, PublicKey) {
+        let (_, recipient) = make_random_keys();
+        let (_, sender) = make_random_keys();
+        (recipient, sender)
+    }
+
+    #[test]
+    fn claim_accepts_matching_preimage_only() {
+        let (recipient, sender) = keys();
+        let preimage = b"shared secret".to_vec();
+        let hash_lock = Hash::digest(&preimage);
+        let htlc = HtlcOutput::new(&recipient, &sender, hash_lock, 10, 100);
+
+        htlc.validate_claim(&preimage, 9).expect("correct preimage");
+        htlc.validate_claim(b"wrong secret", 9)
+            .expect_err("wrong preimage must fail");
+        htlc.validate_claim(&preimage, 10)
+            .expect_err("claim at the timelock must fail");
+    }
+
+    #[test]
+    fn refund_only_valid_after_timelock() {
+        let (recipient, sender) = keys();
+        let hash_lock = Hash::digest(&b"secret".to_vec());
+        let htlc = HtlcOutput::new(&recipient, &sender, hash_lock, 10, 100);
+
+        htlc.validate_refund(9).expect_err("too early");
+        htlc.validate_refund(10).expect("timelock reached");
+    }
+}
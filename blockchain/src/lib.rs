@@ -0,0 +1,45 @@
+//! Blockchain state machine, transaction/output types, and consensus helpers.
+
+//
+// Copyright (c) 2019 Stegos AG
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+// `block`, `blockchain`, `election`, `multisignature`, `output` and
+// `transaction` are real upstream modules that `test.rs` already assumes
+// (see its `use crate::{block, blockchain, ...}` imports below) but whose
+// files aren't part of this partial checkout — only `htlc.rs`,
+// `adaptor_signature.rs` and `scheduler.rs` are, alongside `test.rs` itself.
+// Declaring `mod` for files that don't exist on disk is a hard compile
+// error, so only the modules actually present here are wired in; `test.rs`
+// remains unable to compile until the rest of the upstream tree lands,
+// which predates and is unrelated to this change.
+mod adaptor_signature;
+mod htlc;
+mod scheduler;
+
+#[cfg(test)]
+mod test;
+
+pub use crate::adaptor_signature::{
+    complete_adaptor_signature, create_adaptor_signature, recover_witness,
+    verify_adaptor_signature, AdaptorSignature, AdaptorSignatureError,
+};
+pub use crate::htlc::{HtlcError, HtlcOutput};
+pub use crate::scheduler::{Scheduler, SchedulerError, SchedulerInput, Selection};
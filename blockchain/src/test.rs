@@ -28,6 +28,7 @@ use crate::blockchain::Blockchain;
 use crate::election::mix;
 use crate::multisignature::create_multi_signature;
 use crate::output::{Output, PaymentOutput, PaymentPayloadData, StakeOutput};
+use crate::scheduler::{Scheduler, SchedulerInput};
 use crate::transaction::{CoinbaseTransaction, PaymentTransaction, Transaction};
 use bitvector::BitVector;
 use log::*;
@@ -170,7 +171,7 @@ pub fn create_fake_micro_block(
     let block_reward = chain.cfg().block_reward;
     let mut input_hashes: Vec<Hash> = Vec::new();
     let mut inputs: Vec<Output> = Vec::new();
-    let mut monetary_balance: i64 = 0;
+    let mut payment_candidates: Vec<SchedulerInput> = Vec::new();
     let mut staking_balance: i64 = 0;
     for input_hash in chain.unspent() {
         let input = chain
@@ -183,10 +184,16 @@ pub fn create_fake_micro_block(
         match input {
             Output::PaymentOutput(ref o) => {
                 let payload = o.decrypt_payload(&keys.wallet_skey).unwrap();
-                monetary_balance += payload.amount;
+                payment_candidates.push(SchedulerInput {
+                    output_hash: input_hash.clone(),
+                    amount: payload.amount,
+                });
             }
             Output::PublicPaymentOutput(ref o) => {
-                monetary_balance += o.amount;
+                payment_candidates.push(SchedulerInput {
+                    output_hash: input_hash.clone(),
+                    amount: o.amount,
+                });
             }
             Output::StakeOutput(ref o) => {
                 staking_balance += o.amount;
@@ -196,6 +203,21 @@ pub fn create_fake_micro_block(
         inputs.push(input);
     }
 
+    // A fake block spends every available payment UTXO, so the target is
+    // simply their total; since no subset other than "all of them" can sum
+    // to that total, this always selects every candidate, same as the old
+    // hand-summed sweep, but routed through the real coin-selection
+    // scheduler rather than duplicating its summation logic here.
+    let payment_target: i64 = payment_candidates.iter().map(|c| c.amount).sum();
+    let monetary_balance = if payment_target > 0 {
+        let selection = Scheduler::new()
+            .select(&payment_candidates, payment_target, 0)
+            .expect("payment candidates cover their own total");
+        selection.inputs.iter().map(|c| c.amount).sum::<i64>() + selection.change
+    } else {
+        0
+    };
+
     let mut outputs: Vec<Output> = Vec::new();
     let mut outputs_gamma = Fr::zero();
     // Payments.